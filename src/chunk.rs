@@ -1,9 +1,42 @@
-use std::{convert::TryFrom, convert::TryInto} ;
+use std::convert::TryFrom;
 use std::fmt;
-use crc::crc32::checksum_ieee;
+use std::io::{self, Read, Write};
+use crc::crc32::{self, Hasher32};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chacha20poly1305::aead::{Aead, NewAead};
+use hmac::Hmac;
+use pbkdf2::pbkdf2;
+use rand::RngCore;
+use sha2::Sha256;
 
 use crate::{Result, Error};
 use crate::chunk_type::ChunkType;
+use crate::encode::Encode;
+
+/// Size in bytes of the random nonce prepended to every encrypted payload.
+const NONCE_LEN: usize = 12;
+
+/// Size in bytes of the random per-chunk salt used when deriving the AEAD
+/// key from a passphrase.
+const SALT_LEN: usize = 16;
+
+/// Iteration count for the PBKDF2 passphrase KDF. Chosen to make offline
+/// brute-force of a human passphrase expensive without being noticeable
+/// for a single chunk.
+const KDF_ROUNDS: u32 = 210_000;
+
+/// PNG forbids chunk data lengths above 2^31 - 1 bytes.
+const MAX_CHUNK_DATA_LENGTH: u32 = (1u32 << 31) - 1;
+
+/// Computes the CRC over the type bytes and data by feeding both through
+/// the digest directly, instead of first concatenating them into a
+/// throwaway `Vec`.
+fn compute_crc(chunk_type: &ChunkType, chunk_data: &[u8]) -> u32 {
+    let mut digest = crc32::Digest::new(crc32::IEEE);
+    digest.write(&chunk_type.bytes());
+    digest.write(chunk_data);
+    digest.sum32()
+}
 
 #[derive(Debug)]
 pub struct Chunk {
@@ -16,18 +49,12 @@ pub struct Chunk {
 impl Chunk {
     pub fn new(chunk_type: ChunkType, chunk_data: Vec<u8>) -> Chunk {
         let data_length = chunk_data.len() as u32;
+        let crc = compute_crc(&chunk_type, &chunk_data);
 
-        let c: Vec<u8> = chunk_type.bytes()
-        .iter()
-        .cloned()
-        .chain(chunk_data.iter().cloned())
-        .collect();
-        let crc = checksum_ieee(&c);
-
-        Chunk { 
-            data_length, 
-            chunk_type, 
-            chunk_data, 
+        Chunk {
+            data_length,
+            chunk_type,
+            chunk_data,
             crc,
         }
     }
@@ -45,12 +72,13 @@ impl Chunk {
     }
 
     pub fn crc(&self) -> u32 {
-        let c: Vec<u8> = self.chunk_type.bytes()
-            .iter()
-            .cloned()
-            .chain(self.data().iter().cloned())
-            .collect();
-        checksum_ieee(&c)
+        compute_crc(&self.chunk_type, &self.chunk_data)
+    }
+
+    /// Recomputes the CRC over the stored type and data and compares it
+    /// against the `crc` field, as a cheap standalone integrity check.
+    pub fn verify_crc(&self) -> bool {
+        self.crc == compute_crc(&self.chunk_type, &self.chunk_data)
     }
 
     pub fn data_as_string(&self) -> Result<String> {
@@ -65,52 +93,147 @@ impl Chunk {
     }
 
     pub fn as_bytes(&self) -> Vec<u8> {
-        let chunk = self.data_length.to_be_bytes()
-            .iter()
-            .chain(self.chunk_type.bytes().iter())
-            .chain(self.chunk_data.iter())
-            .chain(self.crc.to_be_bytes().iter())
-            .copied()
-            .collect();
-        chunk
+        let mut buf = Vec::with_capacity(self.encoded_len());
+        self.encode_into(&mut buf);
+        buf
     }
-}
 
-impl TryFrom<&[u8]> for Chunk {
-    type Error = Error;
+    /// Reads a single chunk incrementally from `reader` instead of requiring
+    /// the whole chunk (or PNG) to already be buffered in memory.
+    ///
+    /// `data_length` is validated against the PNG maximum before any
+    /// allocation happens, so a corrupt or hostile stream can't make the
+    /// payload buffer larger than 2^31 - 1 bytes.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Chunk> {
+        let mut data_length_buf = [0u8; 4];
+        read_exact_or_eof(reader, &mut data_length_buf)?;
+        let data_length = u32::from_be_bytes(data_length_buf);
+
+        if data_length > MAX_CHUNK_DATA_LENGTH {
+            return Err(Box::new(ChunkError::LengthTooLarge(data_length)));
+        }
 
-    fn try_from(bytes: &[u8]) -> Result<Self> {
-        //TODOcheck len of bytes
+        let mut chunk_type_buf = [0u8; 4];
+        read_exact_or_eof(reader, &mut chunk_type_buf)?;
+        let chunk_type = ChunkType::try_from(chunk_type_buf)?;
 
-        // Reading data_length
-        let (data_length_buf, bytes) = bytes.split_at(4);
-        let data_length = u32::from_be_bytes(data_length_buf.try_into()?);
+        let mut chunk_data = vec![0u8; data_length as usize];
+        read_exact_or_eof(reader, &mut chunk_data)?;
 
-        // Reading chunk_type
-        let (chunk_type_buf, bytes) = bytes.split_at(4);
-        let c: [u8; 4] = chunk_type_buf.try_into()?;
-        let chunk_type = ChunkType::try_from(c).unwrap();
+        let mut crc_buf = [0u8; 4];
+        read_exact_or_eof(reader, &mut crc_buf)?;
+        let crc = u32::from_be_bytes(crc_buf);
 
-        // Reading chunk_data
-        let (chunk_data, bytes) = bytes.split_at(data_length as usize);
-        let chunk_data: Vec<u8> = chunk_data.to_vec();
-        
-        // Reading crc
-        let (bytes, _) = bytes.split_at(4);
-        let crc = u32::from_be_bytes(bytes.try_into()?);
-        let b: Vec<u8> = chunk_type_buf.iter().chain(chunk_data.iter()).copied().collect();
-        let calculated_crc = checksum_ieee(&b);
-
-        if crc == calculated_crc {
-            Ok(Chunk {
-                data_length,
-                chunk_type,
-                chunk_data,
-                crc,
-            })
-        } else {
-            Err(Box::new(ChunkError::InvalidCRC))
+        if crc != compute_crc(&chunk_type, &chunk_data) {
+            return Err(Box::new(ChunkError::InvalidCRC));
         }
+
+        Ok(Chunk {
+            data_length,
+            chunk_type,
+            chunk_data,
+            crc,
+        })
+    }
+
+    /// Writes this chunk to `writer` in the same length-type-data-crc layout
+    /// produced by `as_bytes`, without requiring the caller to buffer the
+    /// whole serialized PNG.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> Result<()> {
+        writer.write_all(&self.as_bytes())?;
+        Ok(())
+    }
+
+    /// Builds a chunk whose data is `plaintext` encrypted with a key
+    /// derived from `passphrase`, using an AEAD cipher so the payload is
+    /// both confidential and tamper-evident. A random per-chunk salt (used
+    /// to derive the key) and nonce are generated per call and stored
+    /// ahead of the ciphertext.
+    ///
+    /// The CRC is computed over the ciphertext as usual, so the chunk
+    /// remains valid to any PNG reader; only callers with the passphrase
+    /// can recover the original message via `decrypt_data`.
+    pub fn new_encrypted(chunk_type: ChunkType, plaintext: &[u8], passphrase: &[u8]) -> Result<Chunk> {
+        let mut salt = [0u8; SALT_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut salt);
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&derive_key(passphrase, &salt)));
+
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        rand::rngs::OsRng.fill_bytes(&mut nonce_bytes);
+
+        let ciphertext = cipher
+            .encrypt(Nonce::from_slice(&nonce_bytes), plaintext)
+            .map_err(|_| Box::new(ChunkError::EncryptionFailed) as Error)?;
+
+        let mut chunk_data = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+        chunk_data.extend_from_slice(&salt);
+        chunk_data.extend_from_slice(&nonce_bytes);
+        chunk_data.extend_from_slice(&ciphertext);
+
+        Ok(Chunk::new(chunk_type, chunk_data))
+    }
+
+    /// Authenticates and decrypts a chunk built by `new_encrypted`, using
+    /// the same passphrase. Fails with `ChunkError::DecryptionFailed` if
+    /// the tag doesn't match, whether because of a wrong passphrase or a
+    /// tampered payload.
+    pub fn decrypt_data(&self, passphrase: &[u8]) -> Result<Vec<u8>> {
+        if self.chunk_data.len() < SALT_LEN + NONCE_LEN {
+            return Err(Box::new(ChunkError::DecryptionFailed));
+        }
+        let (salt, rest) = self.chunk_data.split_at(SALT_LEN);
+        let (nonce_bytes, ciphertext) = rest.split_at(NONCE_LEN);
+
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&derive_key(passphrase, salt)));
+        cipher
+            .decrypt(Nonce::from_slice(nonce_bytes), ciphertext)
+            .map_err(|_| Box::new(ChunkError::DecryptionFailed) as Error)
+    }
+}
+
+/// Derives a fixed-size AEAD key from an arbitrary-length passphrase and a
+/// per-chunk salt via PBKDF2-HMAC-SHA256, so brute-forcing a human
+/// passphrase is expensive rather than a single fast hash.
+fn derive_key(passphrase: &[u8], salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    pbkdf2::<Hmac<Sha256>>(passphrase, salt, KDF_ROUNDS, &mut key);
+    key
+}
+
+impl Encode for Chunk {
+    fn encoded_len(&self) -> usize {
+        4 + self.chunk_type.encoded_len() + self.chunk_data.len() + 4
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.data_length.to_be_bytes());
+        self.chunk_type.encode_into(buf);
+        buf.extend_from_slice(&self.chunk_data);
+        buf.extend_from_slice(&self.crc.to_be_bytes());
+    }
+}
+
+/// Like `Read::read_exact`, but maps a short read to `ChunkError::UnexpectedEof`
+/// instead of the generic `io::Error` so callers get a PNG-specific reason.
+fn read_exact_or_eof<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<()> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+            Err(Box::new(ChunkError::UnexpectedEof))
+        }
+        Err(e) => Err(Box::new(e)),
+    }
+}
+
+impl TryFrom<&[u8]> for Chunk {
+    type Error = Error;
+
+    /// Delegates to `read_from` so a truncated or otherwise malformed
+    /// buffer (e.g. a `data_length` larger than the remaining bytes)
+    /// surfaces as a `ChunkError` instead of panicking in `split_at`.
+    fn try_from(bytes: &[u8]) -> Result<Self> {
+        Chunk::read_from(&mut io::Cursor::new(bytes))
     }
 }
 
@@ -130,6 +253,11 @@ impl fmt::Display for Chunk {
 pub enum ChunkError {
     InvalidCRC,
     UTF8UncompatibleChunk,
+    LengthTooLarge(u32),
+    UnexpectedEof,
+    InvalidTextChunk,
+    EncryptionFailed,
+    DecryptionFailed,
 }
 
 impl std::error::Error for ChunkError {}
@@ -143,6 +271,21 @@ impl fmt::Display for ChunkError {
             Self::UTF8UncompatibleChunk=> {
                 write!(f, "Chunk data is not compatible with UTF-8")
             }
+            Self::LengthTooLarge(len) => {
+                write!(f, "Chunk data length {} exceeds the maximum of {}", len, MAX_CHUNK_DATA_LENGTH)
+            }
+            Self::UnexpectedEof => {
+                write!(f, "Unexpected end of input while reading chunk")
+            }
+            Self::InvalidTextChunk => {
+                write!(f, "Malformed text chunk layout")
+            }
+            Self::EncryptionFailed => {
+                write!(f, "Failed to encrypt chunk data")
+            }
+            Self::DecryptionFailed => {
+                write!(f, "Failed to decrypt chunk data: wrong key or tampered payload")
+            }
         }
     }
 }
@@ -222,6 +365,26 @@ mod tests {
         assert_eq!(chunk.crc(), 2882656334);
     }
 
+    #[test]
+    fn test_chunk_from_bytes_with_invalid_type_returns_error() {
+        let data_length: u32 = 5;
+        let chunk_type = [82, 117, 49, 116]; // 'u1t' is not ASCII alphabetic
+        let message_bytes = "hello".as_bytes();
+        let crc: u32 = 0;
+
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(message_bytes.iter())
+            .chain(crc.to_be_bytes().iter())
+            .copied()
+            .collect();
+
+        let chunk = Chunk::try_from(chunk_data.as_ref());
+        assert!(chunk.is_err());
+    }
+
     #[test]
     fn test_invalid_chunk_from_bytes() {
         let data_length: u32 = 42;
@@ -243,6 +406,131 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_chunk_from_bytes_with_truncated_data_does_not_panic() {
+        let data_length: u32 = 42;
+        let chunk_type = "RuSt".as_bytes();
+
+        // Claims 42 bytes of data but only provides a handful.
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(b"too short".iter())
+            .copied()
+            .collect();
+
+        let chunk = Chunk::try_from(chunk_data.as_ref());
+        assert!(chunk.is_err());
+    }
+
+    #[test]
+    fn test_chunk_from_bytes_with_empty_input_does_not_panic() {
+        let chunk = Chunk::try_from(&[][..]);
+        assert!(chunk.is_err());
+    }
+
+    #[test]
+    fn test_chunk_verify_crc_valid() {
+        let chunk = testing_chunk();
+        assert!(chunk.verify_crc());
+    }
+
+    #[test]
+    fn test_chunk_verify_crc_invalid() {
+        let chunk_type = ChunkType::try_from(*b"RuSt").unwrap();
+        let chunk = Chunk::new(chunk_type, b"some data".to_vec());
+        let tampered = Chunk { crc: chunk.crc().wrapping_add(1), ..chunk };
+        assert!(!tampered.verify_crc());
+    }
+
+    #[test]
+    fn test_chunk_encrypted_round_trip() {
+        let chunk_type = ChunkType::try_from(*b"RuSt").unwrap();
+        let plaintext = b"This is where your secret message will be!";
+
+        let chunk = Chunk::new_encrypted(chunk_type, plaintext, b"correct horse battery staple").unwrap();
+        let decrypted = chunk.decrypt_data(b"correct horse battery staple").unwrap();
+
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn test_chunk_decrypt_wrong_key_fails() {
+        let chunk_type = ChunkType::try_from(*b"RuSt").unwrap();
+        let plaintext = b"This is where your secret message will be!";
+
+        let chunk = Chunk::new_encrypted(chunk_type, plaintext, b"correct horse battery staple").unwrap();
+        let result = chunk.decrypt_data(b"wrong passphrase");
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_chunk_encrypted_chunk_has_valid_crc() {
+        let chunk_type = ChunkType::try_from(*b"RuSt").unwrap();
+        let chunk = Chunk::new_encrypted(chunk_type, b"secret", b"passphrase").unwrap();
+
+        assert!(chunk.verify_crc());
+    }
+
+    #[test]
+    fn test_chunk_read_from_matches_try_from() {
+        let data_length: u32 = 42;
+        let chunk_type = "RuSt".as_bytes();
+        let message_bytes = "This is where your secret message will be!".as_bytes();
+        let crc: u32 = 2882656334;
+
+        let chunk_data: Vec<u8> = data_length
+            .to_be_bytes()
+            .iter()
+            .chain(chunk_type.iter())
+            .chain(message_bytes.iter())
+            .chain(crc.to_be_bytes().iter())
+            .copied()
+            .collect();
+
+        let chunk = Chunk::read_from(&mut chunk_data.as_slice()).unwrap();
+
+        assert_eq!(chunk.length(), 42);
+        assert_eq!(chunk.chunk_type().to_string(), String::from("RuSt"));
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    fn test_chunk_read_from_rejects_length_too_large() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&u32::MAX.to_be_bytes());
+        bytes.extend_from_slice(b"RuSt");
+
+        let result = Chunk::read_from(&mut bytes.as_slice());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_chunk_read_from_rejects_truncated_input() {
+        let mut bytes = Vec::new();
+        bytes.extend_from_slice(&42u32.to_be_bytes());
+        bytes.extend_from_slice(b"RuSt");
+        bytes.extend_from_slice(b"too short");
+
+        let result = Chunk::read_from(&mut bytes.as_slice());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_chunk_write_to_round_trips() {
+        let chunk = testing_chunk();
+
+        let mut out = Vec::new();
+        chunk.write_to(&mut out).unwrap();
+
+        let round_tripped = Chunk::read_from(&mut out.as_slice()).unwrap();
+        assert_eq!(round_tripped.length(), chunk.length());
+        assert_eq!(round_tripped.chunk_type().to_string(), chunk.chunk_type().to_string());
+        assert_eq!(round_tripped.data(), chunk.data());
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
@@ -5,12 +5,23 @@ use std::fmt;
 use std::cmp;
 
 use crate::{Result, Error};
+use crate::encode::Encode;
 
 #[derive(cmp::PartialEq, cmp::Eq, Debug)]
 pub struct ChunkType {
     chunk: [u8; 4],
 }
 
+impl Encode for ChunkType {
+    fn encoded_len(&self) -> usize {
+        self.chunk.len()
+    }
+
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.chunk);
+    }
+}
+
 impl ChunkType {
     pub fn bytes(&self) -> [u8; 4] {
         self.chunk
@@ -40,6 +51,66 @@ impl ChunkType {
     pub fn is_safe_to_copy(&self) -> bool {
         u8::is_ascii_lowercase(&self.chunk[3])
     }
+
+    pub fn set_critical(&mut self, critical: bool) {
+        set_case(&mut self.chunk[0], critical);
+    }
+
+    pub fn set_public(&mut self, public: bool) {
+        set_case(&mut self.chunk[1], public);
+    }
+
+    pub fn set_reserved_valid(&mut self, valid: bool) {
+        set_case(&mut self.chunk[2], valid);
+    }
+
+    pub fn set_safe_to_copy(&mut self, safe: bool) {
+        set_case(&mut self.chunk[3], !safe);
+    }
+
+    /// Starts a `ChunkTypeBuilder` from a base four-letter code, to
+    /// compose the critical/public/reserved/safe-to-copy flags fluently
+    /// before validating the result.
+    pub fn builder(base: [u8; 4]) -> ChunkTypeBuilder {
+        ChunkTypeBuilder { chunk: base }
+    }
+}
+
+/// Toggles the ASCII case of `byte` to `uppercase`, leaving non-ASCII-alphabetic
+/// bytes untouched (validation of the final bytes happens in `try_from`).
+fn set_case(byte: &mut u8, uppercase: bool) {
+    *byte = if uppercase { byte.to_ascii_uppercase() } else { byte.to_ascii_lowercase() };
+}
+
+/// Fluent builder for `ChunkType`, returned by `ChunkType::builder`.
+pub struct ChunkTypeBuilder {
+    chunk: [u8; 4],
+}
+
+impl ChunkTypeBuilder {
+    pub fn critical(mut self, critical: bool) -> Self {
+        set_case(&mut self.chunk[0], critical);
+        self
+    }
+
+    pub fn public(mut self, public: bool) -> Self {
+        set_case(&mut self.chunk[1], public);
+        self
+    }
+
+    pub fn reserved_valid(mut self, valid: bool) -> Self {
+        set_case(&mut self.chunk[2], valid);
+        self
+    }
+
+    pub fn safe_to_copy(mut self, safe: bool) -> Self {
+        set_case(&mut self.chunk[3], !safe);
+        self
+    }
+
+    pub fn build(self) -> Result<ChunkType> {
+        ChunkType::try_from(self.chunk)
+    }
 }
 
 impl TryFrom<[u8; 4]> for ChunkType {
@@ -188,6 +259,53 @@ mod tests {
         assert_eq!(&chunk.to_string(), "RuSt");
     }
 
+    #[test]
+    pub fn test_chunk_type_set_critical() {
+        let mut chunk = ChunkType::from_str("ruSt").unwrap();
+        assert!(!chunk.is_critical());
+
+        chunk.set_critical(true);
+        assert!(chunk.is_critical());
+
+        chunk.set_critical(false);
+        assert!(!chunk.is_critical());
+    }
+
+    #[test]
+    pub fn test_chunk_type_set_safe_to_copy() {
+        let mut chunk = ChunkType::from_str("RuST").unwrap();
+        assert!(!chunk.is_safe_to_copy());
+
+        chunk.set_safe_to_copy(true);
+        assert!(chunk.is_safe_to_copy());
+
+        chunk.set_safe_to_copy(false);
+        assert!(!chunk.is_safe_to_copy());
+    }
+
+    #[test]
+    pub fn test_chunk_type_builder() {
+        let chunk = ChunkType::builder(*b"rust")
+            .critical(false)
+            .public(false)
+            .reserved_valid(true)
+            .safe_to_copy(true)
+            .build()
+            .unwrap();
+
+        assert_eq!(&chunk.to_string(), "ruSt");
+        assert!(!chunk.is_critical());
+        assert!(!chunk.is_public());
+        assert!(chunk.is_reserved_bit_valid());
+        assert!(chunk.is_safe_to_copy());
+    }
+
+    #[test]
+    pub fn test_chunk_type_builder_rejects_invalid_bytes() {
+        let chunk = ChunkType::builder(*b"ru1t").build();
+        assert!(chunk.is_err());
+    }
+
     #[test]
     pub fn test_chunk_type_trait_impls() {
         let chunk_type_1: ChunkType = TryFrom::try_from([82, 117, 83, 116]).unwrap();
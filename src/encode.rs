@@ -0,0 +1,9 @@
+/// Types that know how to serialize themselves into an existing buffer.
+///
+/// Implementors report their exact encoded size up front via
+/// `encoded_len`, so callers can pre-size the destination `Vec` instead of
+/// growing it incrementally through `collect`/`extend`.
+pub trait Encode {
+    fn encoded_len(&self) -> usize;
+    fn encode_into(&self, buf: &mut Vec<u8>);
+}
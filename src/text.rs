@@ -0,0 +1,336 @@
+use std::convert::{TryFrom, TryInto};
+use std::io::{Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use crate::chunk::{Chunk, ChunkError};
+use crate::chunk_type::ChunkType;
+use crate::{Error, Result};
+
+/// The only compression method defined by the PNG spec, used by both
+/// `zTXt` and compressed `iTXt` chunks.
+const COMPRESSION_METHOD_ZLIB: u8 = 0;
+
+/// A decoded PNG text chunk: `tEXt`, `zTXt` or international `iTXt`.
+///
+/// `language_tag` and `translated_keyword` are only ever `Some` for
+/// `iTXt`; `tEXt` and `zTXt` leave them `None`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TextChunk {
+    pub keyword: String,
+    pub language_tag: Option<String>,
+    pub translated_keyword: Option<String>,
+    pub compressed: bool,
+    pub text: String,
+}
+
+impl TextChunk {
+    /// Builds an uncompressed `tEXt` chunk.
+    pub fn new_text(keyword: impl Into<String>, text: impl Into<String>) -> TextChunk {
+        TextChunk {
+            keyword: keyword.into(),
+            language_tag: None,
+            translated_keyword: None,
+            compressed: false,
+            text: text.into(),
+        }
+    }
+
+    /// Builds a `zTXt` chunk; the value is deflated when converted to a `Chunk`.
+    pub fn new_compressed_text(keyword: impl Into<String>, text: impl Into<String>) -> TextChunk {
+        TextChunk {
+            keyword: keyword.into(),
+            language_tag: None,
+            translated_keyword: None,
+            compressed: true,
+            text: text.into(),
+        }
+    }
+
+    /// Builds an `iTXt` chunk, optionally compressed.
+    pub fn new_international_text(
+        keyword: impl Into<String>,
+        language_tag: impl Into<String>,
+        translated_keyword: impl Into<String>,
+        text: impl Into<String>,
+        compressed: bool,
+    ) -> TextChunk {
+        TextChunk {
+            keyword: keyword.into(),
+            language_tag: Some(language_tag.into()),
+            translated_keyword: Some(translated_keyword.into()),
+            compressed,
+            text: text.into(),
+        }
+    }
+
+    /// Serializes this text chunk into a `tEXt`, `zTXt` or `iTXt` `Chunk`,
+    /// deflating the value first when `compressed` is set.
+    ///
+    /// `keyword`, `language_tag` and `translated_keyword` are NUL-terminated
+    /// fields in the on-disk layout, so any of them containing an embedded
+    /// NUL would silently shift the field boundaries `from_chunk` looks for;
+    /// that's rejected here rather than producing a mis-framed chunk.
+    pub fn to_chunk(&self) -> Result<Chunk> {
+        if self.keyword.contains('\0')
+            || self.language_tag.as_deref().is_some_and(|s| s.contains('\0'))
+            || self.translated_keyword.as_deref().is_some_and(|s| s.contains('\0'))
+        {
+            return Err(Box::new(ChunkError::InvalidTextChunk));
+        }
+
+        let chunk_type = if self.language_tag.is_some() || self.translated_keyword.is_some() {
+            "iTXt"
+        } else if self.compressed {
+            "zTXt"
+        } else {
+            "tEXt"
+        };
+
+        let mut data = Vec::new();
+        data.extend_from_slice(&encode_latin1(&self.keyword)?);
+        data.push(0);
+
+        if chunk_type == "iTXt" {
+            data.push(self.compressed as u8);
+            data.push(COMPRESSION_METHOD_ZLIB);
+            data.extend_from_slice(&encode_latin1(self.language_tag.as_deref().unwrap_or(""))?);
+            data.push(0);
+            data.extend_from_slice(self.translated_keyword.as_deref().unwrap_or("").as_bytes());
+            data.push(0);
+        } else if chunk_type == "zTXt" {
+            data.push(COMPRESSION_METHOD_ZLIB);
+        }
+
+        // iTXt text is UTF-8 per spec; tEXt/zTXt text is Latin-1.
+        let text_bytes = if chunk_type == "iTXt" {
+            self.text.as_bytes().to_vec()
+        } else {
+            encode_latin1(&self.text)?
+        };
+
+        if self.compressed {
+            data.extend(deflate(&text_bytes)?);
+        } else {
+            data.extend_from_slice(&text_bytes);
+        }
+
+        let chunk_type_bytes: [u8; 4] = chunk_type.as_bytes().try_into().unwrap();
+        Ok(Chunk::new(ChunkType::try_from(chunk_type_bytes)?, data))
+    }
+
+    /// Parses a `tEXt`, `zTXt` or `iTXt` chunk, inflating the value when
+    /// the chunk is compressed.
+    pub fn from_chunk(chunk: &Chunk) -> Result<TextChunk> {
+        match chunk.chunk_type().to_string().as_str() {
+            "tEXt" => Self::from_text(chunk.data()),
+            "zTXt" => Self::from_compressed_text(chunk.data()),
+            "iTXt" => Self::from_international_text(chunk.data()),
+            _ => Err(Box::new(ChunkError::InvalidTextChunk)),
+        }
+    }
+
+    fn from_text(data: &[u8]) -> Result<TextChunk> {
+        let (keyword, rest) = split_on_null_latin1(data)?;
+        Ok(TextChunk {
+            keyword,
+            language_tag: None,
+            translated_keyword: None,
+            compressed: false,
+            text: decode_latin1(rest),
+        })
+    }
+
+    fn from_compressed_text(data: &[u8]) -> Result<TextChunk> {
+        let (keyword, rest) = split_on_null_latin1(data)?;
+        let (&compression_method, compressed_text) = rest.split_first().ok_or_else(|| Box::new(ChunkError::InvalidTextChunk) as Error)?;
+        if compression_method != COMPRESSION_METHOD_ZLIB {
+            return Err(Box::new(ChunkError::InvalidTextChunk));
+        }
+
+        let text = inflate(compressed_text)?;
+        Ok(TextChunk {
+            keyword,
+            language_tag: None,
+            translated_keyword: None,
+            compressed: true,
+            text: decode_latin1(&text),
+        })
+    }
+
+    fn from_international_text(data: &[u8]) -> Result<TextChunk> {
+        let (keyword, rest) = split_on_null_latin1(data)?;
+
+        let (&compression_flag, rest) = rest.split_first().ok_or_else(|| Box::new(ChunkError::InvalidTextChunk) as Error)?;
+        let (&compression_method, rest) = rest.split_first().ok_or_else(|| Box::new(ChunkError::InvalidTextChunk) as Error)?;
+
+        let (language_tag, rest) = split_on_null_latin1(rest)?;
+        let (translated_keyword, rest) = split_on_null_utf8(rest)?;
+
+        let compressed = compression_flag != 0;
+        if compressed && compression_method != COMPRESSION_METHOD_ZLIB {
+            return Err(Box::new(ChunkError::InvalidTextChunk));
+        }
+
+        // iTXt text is UTF-8 per spec, even after inflating.
+        let text_bytes = if compressed { inflate(rest)? } else { rest.to_vec() };
+        let text = String::from_utf8(text_bytes).map_err(|_| Box::new(ChunkError::InvalidTextChunk) as Error)?;
+
+        Ok(TextChunk {
+            keyword,
+            language_tag: Some(language_tag),
+            translated_keyword: Some(translated_keyword),
+            compressed,
+            text,
+        })
+    }
+}
+
+/// Splits `data` on the first NUL byte, decoding the leading bytes as
+/// Latin-1 (the encoding the PNG spec mandates for keywords and language
+/// tags, where every byte 0-255 maps directly to the same code point).
+/// Errors only if there's no NUL separator.
+fn split_on_null_latin1(data: &[u8]) -> Result<(String, &[u8])> {
+    let null_pos = data.iter().position(|&b| b == 0).ok_or_else(|| Box::new(ChunkError::InvalidTextChunk) as Error)?;
+    let (head, tail) = data.split_at(null_pos);
+    Ok((decode_latin1(head), &tail[1..]))
+}
+
+/// Splits `data` on the first NUL byte, decoding the leading bytes as
+/// UTF-8 (used for the `iTXt` translated keyword). Errors if there's no
+/// NUL or the leading bytes aren't valid UTF-8.
+fn split_on_null_utf8(data: &[u8]) -> Result<(String, &[u8])> {
+    let null_pos = data.iter().position(|&b| b == 0).ok_or_else(|| Box::new(ChunkError::InvalidTextChunk) as Error)?;
+    let (head, tail) = data.split_at(null_pos);
+    let head = String::from_utf8(head.to_vec()).map_err(|_| Box::new(ChunkError::InvalidTextChunk) as Error)?;
+    Ok((head, &tail[1..]))
+}
+
+/// Decodes Latin-1 bytes to a `String`: every byte maps directly to the
+/// Unicode code point of the same value, so this never fails.
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&b| b as char).collect()
+}
+
+/// Encodes a `String` as Latin-1, failing if it contains a code point above
+/// `0xFF` that Latin-1 can't represent.
+fn encode_latin1(s: &str) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(s.len());
+    for c in s.chars() {
+        let code_point = c as u32;
+        if code_point > 0xFF {
+            return Err(Box::new(ChunkError::InvalidTextChunk));
+        }
+        out.push(code_point as u8);
+    }
+    Ok(out)
+}
+
+fn inflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut decoder = ZlibDecoder::new(data);
+    let mut out = Vec::new();
+    decoder.read_to_end(&mut out).map_err(|_| Box::new(ChunkError::InvalidTextChunk) as Error)?;
+    Ok(out)
+}
+
+fn deflate(data: &[u8]) -> Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data)?;
+    Ok(encoder.finish()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_chunk_round_trip() {
+        let text_chunk = TextChunk::new_text("Title", "pngme hidden message");
+        let chunk = text_chunk.to_chunk().unwrap();
+        assert_eq!(chunk.chunk_type().to_string(), "tEXt");
+
+        let decoded = TextChunk::from_chunk(&chunk).unwrap();
+        assert_eq!(decoded, text_chunk);
+    }
+
+    #[test]
+    fn test_compressed_text_chunk_round_trip() {
+        let text_chunk = TextChunk::new_compressed_text("Comment", "a secret worth compressing");
+        let chunk = text_chunk.to_chunk().unwrap();
+        assert_eq!(chunk.chunk_type().to_string(), "zTXt");
+
+        let decoded = TextChunk::from_chunk(&chunk).unwrap();
+        assert_eq!(decoded, text_chunk);
+    }
+
+    #[test]
+    fn test_international_text_chunk_round_trip() {
+        let text_chunk = TextChunk::new_international_text(
+            "Title",
+            "en",
+            "Titre",
+            "un message secret",
+            true,
+        );
+        let chunk = text_chunk.to_chunk().unwrap();
+        assert_eq!(chunk.chunk_type().to_string(), "iTXt");
+
+        let decoded = TextChunk::from_chunk(&chunk).unwrap();
+        assert_eq!(decoded, text_chunk);
+    }
+
+    #[test]
+    fn test_from_chunk_rejects_unknown_type() {
+        let chunk = Chunk::new(ChunkType::try_from(*b"RuSt").unwrap(), b"not text".to_vec());
+        assert!(TextChunk::from_chunk(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_from_chunk_rejects_missing_null_separator() {
+        let chunk = Chunk::new(ChunkType::try_from(*b"tEXt").unwrap(), b"no separator here".to_vec());
+        assert!(TextChunk::from_chunk(&chunk).is_err());
+    }
+
+    #[test]
+    fn test_to_chunk_rejects_embedded_nul_in_keyword() {
+        let text_chunk = TextChunk::new_text("Ti\u{0}tle", "hello world");
+        assert!(text_chunk.to_chunk().is_err());
+    }
+
+    #[test]
+    fn test_to_chunk_rejects_embedded_nul_in_language_tag() {
+        let text_chunk = TextChunk::new_international_text("Title", "e\u{0}n", "Titre", "text", false);
+        assert!(text_chunk.to_chunk().is_err());
+    }
+
+    #[test]
+    fn test_to_chunk_rejects_embedded_nul_in_translated_keyword() {
+        let text_chunk = TextChunk::new_international_text("Title", "en", "Ti\u{0}tre", "text", false);
+        assert!(text_chunk.to_chunk().is_err());
+    }
+
+    #[test]
+    fn test_from_chunk_decodes_latin1_text() {
+        // "Author\0\xE9" is a valid tEXt chunk whose value is the Latin-1
+        // encoding of "\u{e9}" (e-acute), not valid UTF-8.
+        let mut data = b"Author".to_vec();
+        data.push(0);
+        data.push(0xE9);
+        let chunk = Chunk::new(ChunkType::try_from(*b"tEXt").unwrap(), data);
+
+        let decoded = TextChunk::from_chunk(&chunk).unwrap();
+        assert_eq!(decoded.keyword, "Author");
+        assert_eq!(decoded.text, "\u{e9}");
+    }
+
+    #[test]
+    fn test_text_chunk_round_trip_with_latin1_value() {
+        let text_chunk = TextChunk::new_text("Author", "Caf\u{e9}");
+        let chunk = text_chunk.to_chunk().unwrap();
+
+        let decoded = TextChunk::from_chunk(&chunk).unwrap();
+        assert_eq!(decoded, text_chunk);
+    }
+}